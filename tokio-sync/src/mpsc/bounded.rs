@@ -0,0 +1,233 @@
+use super::chan;
+
+use futures::{Poll, Stream};
+use std::fmt;
+
+/// Send values to the associated `Receiver`.
+pub struct Sender<T> {
+    chan: chan::Tx<T, Semaphore>,
+}
+
+/// A sender handle that does not keep the channel's send half open on its
+/// own. See `Sender::downgrade`.
+pub struct WeakSender<T> {
+    chan: chan::WeakTx<T, Semaphore>,
+}
+
+/// Receive values from the associated `Sender`.
+pub struct Receiver<T> {
+    chan: chan::Rx<T, Semaphore>,
+}
+
+type Semaphore = (::semaphore::Semaphore, usize);
+
+/// Creates a bounded mpsc channel with a single priority band and room for
+/// `buffer` messages.
+pub fn channel<T>(buffer: usize) -> (Sender<T>, Receiver<T>) {
+    channel_with_priorities(buffer, 1)
+}
+
+/// Creates a bounded mpsc channel with `priorities` independent priority
+/// bands, sharing a single buffer of `buffer` messages.
+///
+/// `Sender::try_send_with_priority` selects which band a message lands in;
+/// band `0` is always drained first by the `Receiver`.
+pub fn channel_with_priorities<T>(buffer: usize, priorities: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(buffer > 0, "mpsc bounded channel requires buffer > 0");
+
+    let semaphore = (::semaphore::Semaphore::new(buffer), buffer);
+    let (tx, rx) = chan::channel(semaphore, priorities);
+
+    (Sender::new(tx), Receiver::new(rx))
+}
+
+impl<T> Receiver<T> {
+    pub(crate) fn new(chan: chan::Rx<T, Semaphore>) -> Receiver<T> {
+        Receiver { chan }
+    }
+
+    /// Closes the receiving half of the channel, without dropping it.
+    ///
+    /// This prevents any further messages from being sent on the channel
+    /// while still enabling the receiver to drain messages that are already
+    /// buffered.
+    pub fn close(&mut self) {
+        self.chan.close()
+    }
+
+    /// Attempts to receive the next value without registering a task for
+    /// wakeup.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        self.chan.try_recv().map_err(|e| match e {
+            chan::TryRecvError::Empty => TryRecvError::Empty,
+            chan::TryRecvError::Closed => TryRecvError::Closed,
+        })
+    }
+
+    /// Returns the number of buffer slots currently in use: buffered
+    /// messages plus slots that senders have reserved through `poll_ready`
+    /// but not yet sent into. It is not an exact count of buffered messages.
+    pub fn len(&self) -> usize {
+        self.chan.len()
+    }
+
+    #[doc(hidden)]
+    pub fn poll(&mut self) -> Poll<Option<T>, RecvError> {
+        self.chan.recv().map_err(|_| RecvError(()))
+    }
+}
+
+impl<T> Stream for Receiver<T> {
+    type Item = T;
+    type Error = RecvError;
+
+    fn poll(&mut self) -> Poll<Option<T>, Self::Error> {
+        Receiver::poll(self)
+    }
+}
+
+impl<T> Sender<T> {
+    pub(crate) fn new(chan: chan::Tx<T, Semaphore>) -> Sender<T> {
+        Sender { chan }
+    }
+
+    /// Returns `Ready` when the channel currently has capacity to accept a
+    /// message.
+    pub fn poll_ready(&mut self) -> Poll<(), SendError> {
+        self.chan.poll_ready().map_err(|_| SendError(()))
+    }
+
+    /// Returns `Ready` once the receiver has been closed or dropped.
+    pub fn poll_close(&mut self) -> Poll<(), ()> {
+        self.chan.poll_close()
+    }
+
+    /// Returns the channel's total buffer capacity.
+    pub fn capacity(&self) -> usize {
+        self.chan.capacity()
+    }
+
+    /// Returns the channel's remaining buffer capacity.
+    pub fn available_capacity(&self) -> usize {
+        self.chan.available_capacity()
+    }
+
+    /// Returns a `WeakSender` that does not keep the channel's send half
+    /// open on its own.
+    pub fn downgrade(&self) -> WeakSender<T> {
+        WeakSender {
+            chan: self.chan.downgrade(),
+        }
+    }
+
+    /// Attempts to send a message on the channel's default priority band.
+    ///
+    /// On a channel created with `channel_with_priorities`, this is
+    /// equivalent to `try_send_with_priority(message, 0)`.
+    pub fn try_send(&mut self, message: T) -> Result<(), TrySendError> {
+        self.try_send_with_priority(message, 0)
+    }
+
+    /// Attempts to send a message on the given priority band. Band `0` is
+    /// the highest priority and is always drained first by the `Receiver`.
+    pub fn try_send_with_priority(&mut self, message: T, priority: usize) -> Result<(), TrySendError> {
+        self.chan.try_send(message, priority)
+            .map_err(|()| TrySendError(()))
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Sender<T> {
+        Sender {
+            chan: self.chan.clone(),
+        }
+    }
+}
+
+impl<T> WeakSender<T> {
+    /// Attempts to upgrade the handle to a `Sender`.
+    ///
+    /// Returns `None` if every strong `Sender` has already been dropped.
+    pub fn upgrade(&self) -> Option<Sender<T>> {
+        self.chan.upgrade().map(Sender::new)
+    }
+}
+
+impl<T> Clone for WeakSender<T> {
+    fn clone(&self) -> WeakSender<T> {
+        WeakSender {
+            chan: self.chan.clone(),
+        }
+    }
+}
+
+/// Error returned by `Sender::poll_ready` when the receiver has closed.
+#[derive(Debug)]
+pub struct SendError(pub(crate) ());
+
+impl fmt::Display for SendError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "channel closed")
+    }
+}
+
+/// Error returned by `Sender::try_send`/`try_send_with_priority` when the
+/// channel is at capacity, the priority band does not exist, or the
+/// receiver has closed.
+#[derive(Debug)]
+pub struct TrySendError(pub(crate) ());
+
+impl fmt::Display for TrySendError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "channel full or closed")
+    }
+}
+
+/// Error returned by the `Receiver`'s `Stream` implementation.
+#[derive(Debug)]
+pub struct RecvError(pub(crate) ());
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "channel closed")
+    }
+}
+
+/// Error returned by `Receiver::try_recv`.
+#[derive(Debug)]
+pub enum TryRecvError {
+    /// The channel is currently empty, but the sending half is not closed.
+    /// This operation can be retried.
+    Empty,
+
+    /// The channel's sending half has closed and every buffered value has
+    /// already been received. This operation will never succeed.
+    Closed,
+}
+
+impl TryRecvError {
+    /// Returns `true` if the channel is currently empty, but not closed.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            TryRecvError::Empty => true,
+            TryRecvError::Closed => false,
+        }
+    }
+
+    /// Returns `true` if the channel is closed and drained.
+    pub fn is_closed(&self) -> bool {
+        match self {
+            TryRecvError::Empty => false,
+            TryRecvError::Closed => true,
+        }
+    }
+}
+
+impl fmt::Display for TryRecvError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TryRecvError::Empty => write!(fmt, "receiver has no buffered value"),
+            TryRecvError::Closed => write!(fmt, "channel closed"),
+        }
+    }
+}
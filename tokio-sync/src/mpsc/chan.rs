@@ -3,14 +3,19 @@ use futures::Poll;
 use futures::task::AtomicTask;
 
 use std::cell::UnsafeCell;
-use std::sync::Arc;
-use std::sync::atomic::AtomicUsize;
-use std::sync::atomic::Ordering::{AcqRel, Relaxed};
+use std::sync::{Arc, Mutex, Weak};
+use std::sync::atomic::{AtomicBool, AtomicUsize};
+use std::sync::atomic::Ordering::{AcqRel, Acquire, Relaxed, Release};
 
 /// Channel sender
 pub(crate) struct Tx<T, S: Semaphore> {
     inner: Arc<Chan<T, S>>,
     permit: S::Permit,
+
+    /// This `Tx`'s own task, woken by `Rx::close`/`Rx::drop`. Every `Tx`
+    /// (including clones and upgraded `WeakTx`s) gets its own, since `Chan`
+    /// may have many outstanding senders polling `poll_close` concurrently.
+    waker: Arc<AtomicTask>,
 }
 
 /// Channel receiver
@@ -18,6 +23,23 @@ pub(crate) struct Rx<T, S> {
     inner: Arc<Chan<T, S>>,
 }
 
+/// A sender handle that keeps the channel allocation alive without counting
+/// towards `tx_count`, so it never blocks the "all senders dropped" close
+/// event on its own.
+pub(crate) struct WeakTx<T, S> {
+    inner: Arc<Chan<T, S>>,
+}
+
+/// Error returned by `Rx::try_recv`.
+#[derive(Debug)]
+pub(crate) enum TryRecvError {
+    /// The channel is currently empty, but the send half is not closed.
+    Empty,
+
+    /// The channel is empty and the send half is closed.
+    Closed,
+}
+
 pub trait Semaphore: Sync {
     type Permit;
 
@@ -39,56 +61,112 @@ pub trait Semaphore: Sync {
 }
 
 struct Chan<T, S> {
-    /// Handle to the push half of the lock-free list.
-    tx: list::Tx<T>,
+    /// Handle to the push half of each priority band's lock-free list,
+    /// ordered from highest priority (index `0`) to lowest.
+    tx: Vec<list::Tx<T>>,
 
-    /// Coordinates access to channel's capacity.
+    /// Coordinates access to channel's capacity. Shared across all priority
+    /// bands so the channel has a single total capacity.
     semaphore: S,
 
     /// Receiver task. Notified when a value is pushed into the channel.
     rx_task: AtomicTask,
 
+    /// Registry of every live `Tx`'s waker, notified via `Rx::close`/`Rx`'s
+    /// `Drop` once the receive half is closed or dropped. A plain
+    /// `Mutex<Vec<_>>` is the simplest correct way to wake *every* pending
+    /// sender rather than just the most recent one to call `poll_close`;
+    /// entries are weak so a dropped `Tx` prunes itself out lazily.
+    tx_tasks: Mutex<Vec<Weak<AtomicTask>>>,
+
     /// Tracks the number of outstanding sender handles.
     ///
     /// When this drops to zero, the send half of the channel is closed.
     tx_count: AtomicUsize,
 
+    /// `true` once the `Rx` half has been closed or dropped.
+    rx_closed: AtomicBool,
+
     /// Only accessed by `Rx` handle.
     rx_fields: UnsafeCell<RxFields<T>>,
 }
 
 /// Fields only accessed by `Rx` handle.
 struct RxFields<T> {
-    /// Channel receiver. This field is only accessed by the `Receiver` type.
-    list: list::Rx<T>,
-
-    /// `true` if `Rx::close` is called.
-    rx_closed: bool,
+    /// Channel receiver for each priority band, in the same order as
+    /// `Chan::tx`. This field is only accessed by the `Receiver` type.
+    list: Vec<list::Rx<T>>,
 }
 
 unsafe impl<T: Send, S: Send> Send for Chan<T, S> {}
 unsafe impl<T: Send, S: Sync> Sync for Chan<T, S> {}
 
-pub(crate) fn channel<T, S>(semaphore: S) -> (Tx<T, S>, Rx<T, S>)
+/// Creates a channel with `priorities` independent bands, highest priority
+/// first, sharing a single `semaphore` for total capacity.
+pub(crate) fn channel<T, S>(semaphore: S, priorities: usize) -> (Tx<T, S>, Rx<T, S>)
 where
     S: Semaphore,
 {
-    let (tx, rx) = list::channel();
+    assert!(priorities > 0, "a channel must have at least one priority band");
+
+    let mut tx = Vec::with_capacity(priorities);
+    let mut rx = Vec::with_capacity(priorities);
+
+    for _ in 0..priorities {
+        let (band_tx, band_rx) = list::channel();
+        tx.push(band_tx);
+        rx.push(band_rx);
+    }
 
     let chan = Arc::new(Chan {
         tx,
         semaphore,
         rx_task: AtomicTask::new(),
+        tx_tasks: Mutex::new(Vec::new()),
         tx_count: AtomicUsize::new(1),
+        rx_closed: AtomicBool::new(false),
         rx_fields: UnsafeCell::new(RxFields {
             list: rx,
-            rx_closed: false,
         }),
     });
 
     (Tx::new(chan.clone()), Rx::new(chan))
 }
 
+impl<T, S> Chan<T, S> {
+    /// Registers a fresh waker for a new `Tx` handle and returns it.
+    fn new_tx_waker(&self) -> Arc<AtomicTask> {
+        let waker = Arc::new(AtomicTask::new());
+
+        let mut tasks = self.tx_tasks.lock().unwrap();
+
+        // Opportunistically drop entries for `Tx`s that have since gone
+        // away. Without this, a channel whose `Tx` is cloned and dropped
+        // many times over a long receiver lifetime would grow this `Vec`
+        // without bound, since the only other pruning points are `Rx::close`
+        // and `Rx`'s `Drop`, both reached only once the receive half closes.
+        tasks.retain(|weak| weak.upgrade().is_some());
+        tasks.push(Arc::downgrade(&waker));
+
+        waker
+    }
+
+    /// Wakes every still-live `Tx` waker, pruning any that have been
+    /// dropped.
+    fn notify_tx_tasks(&self) {
+        let mut tasks = self.tx_tasks.lock().unwrap();
+        tasks.retain(|weak| {
+            match weak.upgrade() {
+                Some(task) => {
+                    task.notify();
+                    true
+                }
+                None => false,
+            }
+        });
+    }
+}
+
 // ===== impl Tx =====
 
 impl<T, S> Tx<T, S>
@@ -96,9 +174,12 @@ where
     S: Semaphore,
 {
     fn new(chan: Arc<Chan<T, S>>) -> Tx<T, S> {
+        let waker = chan.new_tx_waker();
+
         Tx {
             inner: chan,
             permit: S::new_permit(),
+            waker,
         }
     }
 
@@ -107,12 +188,20 @@ where
         self.inner.semaphore.poll_acquire(&mut self.permit)
     }
 
-    /// Send a message and notify the receiver.
-    pub fn try_send(&mut self, value: T) -> Result<(), ()> {
+    /// Send a message on the given priority band and notify the receiver.
+    ///
+    /// Band `0` is the highest priority; `Rx::recv` always drains a higher
+    /// band before a lower one. Returns `Err(())` if `priority` does not
+    /// name one of the channel's bands.
+    pub fn try_send(&mut self, value: T, priority: usize) -> Result<(), ()> {
+        if priority >= self.inner.tx.len() {
+            return Err(());
+        }
+
         self.inner.semaphore.try_acquire(&mut self.permit)?;
 
-        // Push the value
-        self.inner.tx.push(value);
+        // Push the value into its priority band
+        self.inner.tx[priority].push(value);
 
         // Notify the rx task
         self.inner.rx_task.notify();
@@ -122,6 +211,33 @@ where
 
         Ok(())
     }
+
+    /// Returns a `WeakTx` pointing at the same channel, without incrementing
+    /// `tx_count`.
+    pub fn downgrade(&self) -> WeakTx<T, S> {
+        WeakTx {
+            inner: self.inner.clone(),
+        }
+    }
+
+    /// Returns `Ready` once the `Rx` half has been closed or dropped.
+    pub fn poll_close(&mut self) -> Poll<(), ()> {
+        use futures::Async::*;
+
+        if self.inner.rx_closed.load(Acquire) {
+            return Ok(Ready(()));
+        }
+
+        self.waker.register();
+
+        // It is possible that the receiver closed between the check above
+        // and registering the task, so check once more.
+        if self.inner.rx_closed.load(Acquire) {
+            Ok(Ready(()))
+        } else {
+            Ok(NotReady)
+        }
+    }
 }
 
 impl<T, S> Clone for Tx<T, S>
@@ -133,9 +249,12 @@ where
         // strong ref to `self`, preventing a concurrent decrement to zero.
         self.inner.tx_count.fetch_add(1, Relaxed);
 
+        let waker = self.inner.new_tx_waker();
+
         Tx {
             inner: self.inner.clone(),
             permit: S::new_permit(),
+            waker,
         }
     }
 }
@@ -151,14 +270,62 @@ where
             return;
         }
 
-        // Close the list, which sends a `Close` message
-        self.inner.tx.close();
+        // Close each band's list, which sends a `Close` message
+        for tx in &self.inner.tx {
+            tx.close();
+        }
 
         // Notify the receiver
         self.inner.rx_task.notify();
     }
 }
 
+// ===== impl WeakTx =====
+
+impl<T, S> WeakTx<T, S>
+where
+    S: Semaphore,
+{
+    /// Attempts to upgrade the handle to a `Tx`, incrementing `tx_count`.
+    ///
+    /// Returns `None` if every strong sender has already been dropped,
+    /// mirroring `std::sync::Weak::upgrade`.
+    pub fn upgrade(&self) -> Option<Tx<T, S>> {
+        let mut curr = self.inner.tx_count.load(Relaxed);
+
+        loop {
+            if curr == 0 {
+                // All strong senders are gone; the channel is closing (or
+                // closed) and must not be resurrected.
+                return None;
+            }
+
+            match self.inner.tx_count.compare_exchange_weak(
+                curr, curr + 1, AcqRel, Relaxed,
+            ) {
+                Ok(_) => {
+                    let waker = self.inner.new_tx_waker();
+
+                    return Some(Tx {
+                        inner: self.inner.clone(),
+                        permit: S::new_permit(),
+                        waker,
+                    });
+                }
+                Err(actual) => curr = actual,
+            }
+        }
+    }
+}
+
+impl<T, S> Clone for WeakTx<T, S> {
+    fn clone(&self) -> WeakTx<T, S> {
+        WeakTx {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
 // ===== impl Rx =====
 
 impl<T, S> Rx<T, S>
@@ -170,37 +337,60 @@ where
     }
 
     pub fn close(&mut self) {
+        self.inner.rx_closed.store(true, Release);
+        self.inner.semaphore.close();
+        self.inner.notify_tx_tasks();
+    }
+
+    /// Attempt to receive the next value without registering a task for
+    /// wakeup.
+    ///
+    /// Bands are scanned from highest priority to lowest, returning the
+    /// first buffered value found. Returns `Err(TryRecvError::Empty)` if
+    /// every band is currently empty but not every band has closed, or
+    /// `Err(TryRecvError::Closed)` once all bands have closed and drained.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        use super::block::Read::*;
+
         let rx_fields = unsafe { &mut *self.inner.rx_fields.get() };
 
-        rx_fields.rx_closed = true;
-        self.inner.semaphore.close();
+        let mut all_closed = true;
+
+        for (list, tx) in rx_fields.list.iter_mut().zip(self.inner.tx.iter()) {
+            match list.pop(tx) {
+                Some(Value(value)) => {
+                    self.inner.semaphore.add_permits(1);
+                    return Ok(value);
+                }
+                Some(Closed) => {} // this band is closed and drained
+                None => all_closed = false, // this band is still open
+            }
+        }
+
+        if all_closed {
+            // TODO: This check may not be required as it most
+            // likely can only return `true` at this point. A
+            // channel is closed when all tx handles are dropped.
+            // Dropping a tx handle releases memory, which ensures
+            // that if dropping the tx handle is visible, then all
+            // messages sent are also visible.
+            assert!(self.inner.semaphore.is_idle());
+            Err(TryRecvError::Closed)
+        } else {
+            Err(TryRecvError::Empty)
+        }
     }
 
     /// Receive the next value
     pub fn recv(&mut self) -> Poll<Option<T>, ()> {
-        use super::block::Read::*;
         use futures::Async::*;
 
-        let rx_fields = unsafe { &mut *self.inner.rx_fields.get() };
-
         macro_rules! try_recv {
             () => {
-                match rx_fields.list.pop(&self.inner.tx) {
-                    Some(Value(value)) => {
-                        self.inner.semaphore.add_permits(1);
-                        return Ok(Ready(Some(value)));
-                    }
-                    Some(Closed) => {
-                        // TODO: This check may not be required as it most
-                        // likely can only return `true` at this point. A
-                        // channel is closed when all tx handles are dropped.
-                        // Dropping a tx handle releases memory, which ensures
-                        // that if dropping the tx handle is visible, then all
-                        // messages sent are also visible.
-                        assert!(self.inner.semaphore.is_idle());
-                        return Ok(Ready(None));
-                    }
-                    None => {} // fall through
+                match self.try_recv() {
+                    Ok(value) => return Ok(Ready(Some(value))),
+                    Err(TryRecvError::Closed) => return Ok(Ready(None)),
+                    Err(TryRecvError::Empty) => {} // fall through
                 }
             }
         }
@@ -215,9 +405,9 @@ where
         try_recv!();
 
         debug!("recv; rx_closed = {:?}; is_idle = {:?}",
-               rx_fields.rx_closed, self.inner.semaphore.is_idle());
+               self.inner.rx_closed.load(Acquire), self.inner.semaphore.is_idle());
 
-        if rx_fields.rx_closed && self.inner.semaphore.is_idle() {
+        if self.inner.rx_closed.load(Acquire) && self.inner.semaphore.is_idle() {
             Ok(Ready(None))
         } else {
             Ok(NotReady)
@@ -225,6 +415,20 @@ where
     }
 }
 
+impl<T, S> Drop for Rx<T, S>
+where
+    S: Semaphore,
+{
+    fn drop(&mut self) {
+        // Ensure senders waiting on `poll_close` are woken, and that
+        // `try_send`/`poll_ready` start failing, even if `close` was never
+        // explicitly called.
+        self.inner.rx_closed.store(true, Release);
+        self.inner.semaphore.close();
+        self.inner.notify_tx_tasks();
+    }
+}
+
 // ===== impl Semaphore for (::Semaphore, capacity) =====
 
 use semaphore::Permit;
@@ -269,4 +473,164 @@ impl Semaphore for (::semaphore::Semaphore, usize) {
     }
 }
 
+impl<T> Tx<T, (::semaphore::Semaphore, usize)> {
+    /// Returns the channel's total buffer capacity.
+    pub fn capacity(&self) -> usize {
+        self.inner.semaphore.1
+    }
+
+    /// Returns the channel's remaining buffer capacity.
+    pub fn available_capacity(&self) -> usize {
+        self.inner.semaphore.0.available_permits()
+    }
+}
+
+impl<T> Rx<T, (::semaphore::Semaphore, usize)> {
+    /// Returns the number of buffer slots currently in use: the capacity
+    /// minus the semaphore's available permits. This counts buffered
+    /// messages plus slots that senders have reserved through `poll_ready`
+    /// but not yet sent into, so it is not an exact count of buffered
+    /// messages.
+    pub fn len(&self) -> usize {
+        self.inner.semaphore.1 - self.inner.semaphore.0.available_permits()
+    }
+}
+
 // ===== impl Semaphore for AtomicUsize =====
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Barrier;
+    use std::thread;
+
+    /// A trivial counting `Semaphore` used so these tests don't depend on
+    /// the real `::semaphore::Semaphore` impl.
+    struct TestSemaphore {
+        permits: AtomicUsize,
+    }
+
+    impl TestSemaphore {
+        fn new(permits: usize) -> TestSemaphore {
+            TestSemaphore {
+                permits: AtomicUsize::new(permits),
+            }
+        }
+    }
+
+    impl Semaphore for TestSemaphore {
+        type Permit = ();
+
+        fn new_permit() {}
+
+        fn drop_permit(&self, _permit: &mut ()) {}
+
+        fn is_idle(&self) -> bool {
+            true
+        }
+
+        fn add_permits(&self, num: usize) {
+            self.permits.fetch_add(num, Relaxed);
+        }
+
+        fn poll_acquire(&self, _permit: &mut ()) -> Poll<(), ()> {
+            use futures::Async::Ready;
+            Ok(Ready(()))
+        }
+
+        fn try_acquire(&self, _permit: &mut ()) -> Result<(), ()> {
+            loop {
+                let curr = self.permits.load(Relaxed);
+
+                if curr == 0 {
+                    return Err(());
+                }
+
+                if self.permits.compare_exchange(curr, curr - 1, Relaxed, Relaxed).is_ok() {
+                    return Ok(());
+                }
+            }
+        }
+
+        fn forget(&self, _permit: &mut ()) {}
+
+        fn close(&self) {}
+    }
+
+    #[test]
+    fn recv_drains_higher_priority_band_first() {
+        let (mut tx, mut rx) = channel(TestSemaphore::new(10), 2);
+
+        tx.try_send("low", 1).unwrap();
+        tx.try_send("high", 0).unwrap();
+
+        assert_eq!(rx.try_recv().unwrap(), "high");
+        assert_eq!(rx.try_recv().unwrap(), "low");
+    }
+
+    #[test]
+    fn try_recv_closed_only_after_every_band_drained() {
+        let (mut tx, mut rx) = channel(TestSemaphore::new(10), 2);
+
+        tx.try_send("band0", 0).unwrap();
+        drop(tx);
+
+        // Band 1 is closed and already drained, but band 0 still has a
+        // buffered value, so the channel as a whole is not yet `Closed`.
+        assert_eq!(rx.try_recv().unwrap(), "band0");
+
+        // Every band is now closed and drained.
+        match rx.try_recv() {
+            Err(TryRecvError::Closed) => {}
+            other => panic!("expected Closed, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn try_send_rejects_out_of_range_priority() {
+        let (mut tx, _rx) = channel(TestSemaphore::new(10), 1);
+
+        assert!(tx.try_send("nope", 1).is_err());
+    }
+
+    #[test]
+    fn weak_tx_upgrade_fails_after_last_strong_drop() {
+        let (tx, rx) = channel::<&'static str, _>(TestSemaphore::new(10), 1);
+
+        let weak = tx.downgrade();
+        let upgraded = weak.upgrade().expect("strong Tx is still alive");
+
+        drop(upgraded);
+        drop(tx);
+        drop(rx);
+
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    fn weak_tx_upgrade_races_final_tx_drop() {
+        for _ in 0..100 {
+            let (tx, rx) = channel::<&'static str, _>(TestSemaphore::new(10), 1);
+            let weak = tx.downgrade();
+            let barrier = Arc::new(Barrier::new(2));
+
+            let dropper = {
+                let barrier = barrier.clone();
+                thread::spawn(move || {
+                    barrier.wait();
+                    drop(tx);
+                })
+            };
+
+            barrier.wait();
+            // Whether this observes the `Tx` just before or just after it is
+            // dropped, it must return a consistent answer without panicking
+            // or deadlocking against the concurrent drop.
+            drop(weak.upgrade());
+
+            dropper.join().unwrap();
+            drop(rx);
+        }
+    }
+}
@@ -45,14 +45,17 @@ mod unbounded;
 
 pub use self::bounded::{
     channel,
+    channel_with_priorities,
     Receiver,
-    Sender
+    Sender,
+    WeakSender,
 };
 
 pub use self::unbounded::{
     unbounded_channel,
     UnboundedReceiver,
     UnboundedSender,
+    WeakUnboundedSender,
 };
 
 pub mod error {
@@ -60,12 +63,13 @@ pub mod error {
         SendError,
         TrySendError,
         RecvError,
+        TryRecvError,
     };
 
     pub use super::unbounded::{
-        UnboundedSendError,
         UnboundedTrySendError,
         UnboundedRecvError,
+        UnboundedTryRecvError,
     };
 }
 
@@ -0,0 +1,173 @@
+use super::chan;
+
+use futures::{Poll, Stream};
+use std::fmt;
+use std::sync::atomic::AtomicUsize;
+
+/// Send values to the associated `UnboundedReceiver`.
+pub struct UnboundedSender<T> {
+    chan: chan::Tx<T, AtomicUsize>,
+}
+
+/// A sender handle that does not keep the channel's send half open on its
+/// own. See `UnboundedSender::downgrade`.
+pub struct WeakUnboundedSender<T> {
+    chan: chan::WeakTx<T, AtomicUsize>,
+}
+
+/// Receive values from the associated `UnboundedSender`.
+pub struct UnboundedReceiver<T> {
+    chan: chan::Rx<T, AtomicUsize>,
+}
+
+/// Creates an unbounded mpsc channel with a single priority band.
+pub fn unbounded_channel<T>() -> (UnboundedSender<T>, UnboundedReceiver<T>) {
+    let (tx, rx) = chan::channel(AtomicUsize::new(0), 1);
+
+    (UnboundedSender::new(tx), UnboundedReceiver::new(rx))
+}
+
+impl<T> UnboundedReceiver<T> {
+    pub(crate) fn new(chan: chan::Rx<T, AtomicUsize>) -> UnboundedReceiver<T> {
+        UnboundedReceiver { chan }
+    }
+
+    /// Closes the receiving half of the channel, without dropping it.
+    pub fn close(&mut self) {
+        self.chan.close()
+    }
+
+    /// Attempts to receive the next value without registering a task for
+    /// wakeup.
+    pub fn try_recv(&mut self) -> Result<T, UnboundedTryRecvError> {
+        self.chan.try_recv().map_err(|e| match e {
+            chan::TryRecvError::Empty => UnboundedTryRecvError::Empty,
+            chan::TryRecvError::Closed => UnboundedTryRecvError::Closed,
+        })
+    }
+
+    #[doc(hidden)]
+    pub fn poll(&mut self) -> Poll<Option<T>, UnboundedRecvError> {
+        self.chan.recv().map_err(|_| UnboundedRecvError(()))
+    }
+}
+
+impl<T> Stream for UnboundedReceiver<T> {
+    type Item = T;
+    type Error = UnboundedRecvError;
+
+    fn poll(&mut self) -> Poll<Option<T>, Self::Error> {
+        UnboundedReceiver::poll(self)
+    }
+}
+
+impl<T> UnboundedSender<T> {
+    pub(crate) fn new(chan: chan::Tx<T, AtomicUsize>) -> UnboundedSender<T> {
+        UnboundedSender { chan }
+    }
+
+    /// Returns `Ready` once the receiver has been closed or dropped.
+    pub fn poll_close(&mut self) -> Poll<(), ()> {
+        self.chan.poll_close()
+    }
+
+    /// Returns a `WeakUnboundedSender` that does not keep the channel's send
+    /// half open on its own.
+    pub fn downgrade(&self) -> WeakUnboundedSender<T> {
+        WeakUnboundedSender {
+            chan: self.chan.downgrade(),
+        }
+    }
+
+    /// Attempts to send a message on the channel.
+    pub fn try_send(&mut self, message: T) -> Result<(), UnboundedTrySendError> {
+        self.chan.try_send(message, 0)
+            .map_err(|()| UnboundedTrySendError(()))
+    }
+}
+
+impl<T> Clone for UnboundedSender<T> {
+    fn clone(&self) -> UnboundedSender<T> {
+        UnboundedSender {
+            chan: self.chan.clone(),
+        }
+    }
+}
+
+impl<T> WeakUnboundedSender<T> {
+    /// Attempts to upgrade the handle to an `UnboundedSender`.
+    ///
+    /// Returns `None` if every strong `UnboundedSender` has already been
+    /// dropped.
+    pub fn upgrade(&self) -> Option<UnboundedSender<T>> {
+        self.chan.upgrade().map(UnboundedSender::new)
+    }
+}
+
+impl<T> Clone for WeakUnboundedSender<T> {
+    fn clone(&self) -> WeakUnboundedSender<T> {
+        WeakUnboundedSender {
+            chan: self.chan.clone(),
+        }
+    }
+}
+
+/// Error returned by `UnboundedSender::try_send` when the receiver has
+/// closed.
+#[derive(Debug)]
+pub struct UnboundedTrySendError(pub(crate) ());
+
+impl fmt::Display for UnboundedTrySendError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "channel closed")
+    }
+}
+
+/// Error returned by the `UnboundedReceiver`'s `Stream` implementation.
+#[derive(Debug)]
+pub struct UnboundedRecvError(pub(crate) ());
+
+impl fmt::Display for UnboundedRecvError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "channel closed")
+    }
+}
+
+/// Error returned by `UnboundedReceiver::try_recv`.
+#[derive(Debug)]
+pub enum UnboundedTryRecvError {
+    /// The channel is currently empty, but the sending half is not closed.
+    /// This operation can be retried.
+    Empty,
+
+    /// The channel's sending half has closed and every buffered value has
+    /// already been received. This operation will never succeed.
+    Closed,
+}
+
+impl UnboundedTryRecvError {
+    /// Returns `true` if the channel is currently empty, but not closed.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            UnboundedTryRecvError::Empty => true,
+            UnboundedTryRecvError::Closed => false,
+        }
+    }
+
+    /// Returns `true` if the channel is closed and drained.
+    pub fn is_closed(&self) -> bool {
+        match self {
+            UnboundedTryRecvError::Empty => false,
+            UnboundedTryRecvError::Closed => true,
+        }
+    }
+}
+
+impl fmt::Display for UnboundedTryRecvError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UnboundedTryRecvError::Empty => write!(fmt, "receiver has no buffered value"),
+            UnboundedTryRecvError::Closed => write!(fmt, "channel closed"),
+        }
+    }
+}